@@ -1,4 +1,9 @@
-use regex::{RegexSet, RegexBuilder};
+use std::collections::HashMap;
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use once_cell::unsync::OnceCell;
+use regex::{Regex, RegexBuilder, RegexSet};
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 
@@ -9,72 +14,903 @@ pub struct Threat {
     pub severity: String,
 }
 
+/// A `Threat` that actually fired against a piece of content, with the
+/// exact substring and position it was found at so editors and structured
+/// logs can point straight at the offending text.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ThreatMatch {
+    pub pattern: String,
+    pub category: String,
+    pub severity: String,
+    pub matched_text: String,
+    pub byte_offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct SanitizeResult {
     pub safe: bool,
-    pub threats: Vec<Threat>,
+    pub threats: Vec<ThreatMatch>,
     pub risk_score: f64,
+    /// Per-severity base weights used for this scan. A threat's actual
+    /// contribution to `risk_score` is `effective_weights[severity] *
+    /// category_multipliers[category]`, further scaled by
+    /// `diminishing_returns.factor` once that severity's hit count within
+    /// this scan reaches `diminishing_returns.threshold`. The summed
+    /// contributions are then multiplied by the scan's `trust_multiplier`
+    /// (not stored here) and clamped to `cap` — reproduce from `threats`,
+    /// these fields and that multiplier to audit `risk_score`.
+    pub effective_weights: HashMap<String, f64>,
+    pub category_multipliers: HashMap<String, f64>,
+    pub diminishing_returns: Option<DiminishingReturns>,
+    pub cap: f64,
+}
+
+/// After a severity has hit `threshold` matches within a single scan,
+/// every further match of that severity is scaled by `factor` so e.g. ten
+/// low-severity hits can't outweigh one critical one.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct DiminishingReturns {
+    pub threshold: usize,
+    pub factor: f64,
+}
+
+/// Data-driven replacement for the hardcoded severity weights and 100.0
+/// cap, so the scoring model is tunable per deployment without
+/// recompiling the WASM module.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ScoringConfig {
+    #[serde(default = "default_severity_weights")]
+    pub severity_weights: HashMap<String, f64>,
+    #[serde(default)]
+    pub category_multipliers: HashMap<String, f64>,
+    #[serde(default = "default_cap")]
+    pub cap: f64,
+    #[serde(default)]
+    pub diminishing_returns: Option<DiminishingReturns>,
+}
+
+impl Default for ScoringConfig {
+    fn default() -> Self {
+        ScoringConfig {
+            severity_weights: default_severity_weights(),
+            category_multipliers: HashMap::new(),
+            cap: default_cap(),
+            diminishing_returns: None,
+        }
+    }
+}
+
+fn default_severity_weights() -> HashMap<String, f64> {
+    [
+        ("critical".to_string(), 10.0),
+        ("high".to_string(), 5.0),
+        ("medium".to_string(), 3.0),
+        ("low".to_string(), 1.0),
+    ]
+    .into_iter()
+    .collect()
+}
+
+fn default_cap() -> f64 {
+    100.0
+}
+
+/// Metadata carried alongside a signed pattern bundle so operators can
+/// confirm which ruleset is actually loaded.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BundleMetadata {
+    pub issuer: String,
+    pub version: String,
+    pub issued_at: String,
+}
+
+/// A pattern set distributed with a detached Ed25519 signature over its
+/// canonical (serialized) pattern list, so an ARI host won't compile a
+/// tampered or untrusted rule list.
+#[derive(Deserialize)]
+pub struct SignedPatternBundle {
+    pub patterns: Vec<Threat>,
+    pub metadata: BundleMetadata,
+    /// Base64-encoded Ed25519 signature over `serde_json::to_vec(&patterns)`.
+    pub signature: String,
+}
+
+/// A contiguous, merged run of matched bytes in the original content, with
+/// the categories of every pattern that matched inside it.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RedactedSpan {
+    pub start: usize,
+    pub end: usize,
+    pub categories: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SanitizeRedactResult {
+    pub result: SanitizeResult,
+    pub redacted: String,
+    pub redactions: Vec<RedactedSpan>,
+}
+
+#[derive(Deserialize)]
+pub struct RedactOptions {
+    #[serde(default = "default_placeholder_template")]
+    pub placeholder_template: String,
+}
+
+impl Default for RedactOptions {
+    fn default() -> Self {
+        RedactOptions {
+            placeholder_template: default_placeholder_template(),
+        }
+    }
+}
+
+fn default_placeholder_template() -> String {
+    "[REDACTED:{category}]".to_string()
+}
+
+#[derive(Deserialize)]
+pub struct BatchDoc {
+    pub name: String,
+    pub content: String,
+    #[serde(default = "default_trust_multiplier")]
+    pub trust_multiplier: f64,
+}
+
+fn default_trust_multiplier() -> f64 {
+    1.0
+}
+
+/// A `ThreatMatch` tagged with the name of the document it came from, so a
+/// batch scan can be traced back to the input that introduced the risk.
+/// Fields are inlined rather than `#[serde(flatten)]`-ing a `ThreatMatch`,
+/// since `flatten` forces serde through its map-serialization path, which
+/// `serde_wasm_bindgen`'s default serializer turns into a JS `Map` instead
+/// of a plain object.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SourcedThreatMatch {
+    pub pattern: String,
+    pub category: String,
+    pub severity: String,
+    pub matched_text: String,
+    pub byte_offset: usize,
+    pub line: usize,
+    pub column: usize,
+    pub source: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct DocumentSanitizeResult {
+    pub name: String,
+    pub result: SanitizeResult,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BatchSanitizeResult {
+    pub safe: bool,
+    pub documents: Vec<DocumentSanitizeResult>,
+    pub threats: Vec<SourcedThreatMatch>,
+    pub worst_risk_score: f64,
+    pub summed_risk_score: f64,
+}
+
+/// A logical condition over base pattern hits. Scoring each pattern in
+/// isolation is easy to evade (e.g. spreading an attack across several
+/// individually-low-severity patterns); these let a rule fire only when a
+/// combination of patterns, a repeat count, or a proximity constraint holds.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CompositeCondition {
+    AllOf { patterns: Vec<String> },
+    AnyOf { patterns: Vec<String> },
+    NoneOf { patterns: Vec<String> },
+    MinCount { pattern: String, count: usize },
+    Proximity { pattern_a: String, pattern_b: String, within_bytes: usize },
+}
+
+impl CompositeCondition {
+    /// Every base pattern string this condition names, regardless of
+    /// variant, so a ruleset reload can check they all still exist.
+    fn referenced_patterns(&self) -> Vec<&str> {
+        match self {
+            CompositeCondition::AllOf { patterns }
+            | CompositeCondition::AnyOf { patterns }
+            | CompositeCondition::NoneOf { patterns } => {
+                patterns.iter().map(String::as_str).collect()
+            }
+            CompositeCondition::MinCount { pattern, .. } => vec![pattern.as_str()],
+            CompositeCondition::Proximity {
+                pattern_a,
+                pattern_b,
+                ..
+            } => vec![pattern_a.as_str(), pattern_b.as_str()],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CompositeRule {
+    pub name: String,
+    pub category: String,
+    pub severity: String,
+    pub condition: CompositeCondition,
+}
+
+/// Serialize to a `JsValue` the way JS callers expect: plain objects for
+/// our `HashMap` fields (`effective_weights`, `category_multipliers`),
+/// not JS `Map`s, which `serde_wasm_bindgen`'s default serializer would
+/// otherwise produce.
+fn to_js_value<T: Serialize + ?Sized>(value: &T) -> Result<JsValue, JsValue> {
+    value
+        .serialize(&serde_wasm_bindgen::Serializer::new().serialize_maps_as_objects(true))
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize result: {}", e)))
 }
 
 #[wasm_bindgen]
 pub struct Sanitizer {
     regex_set: RegexSet,
+    /// One cell per pattern, compiled lazily the first time the cheap
+    /// `regex_set` pass reports a hit for that index — most patterns in a
+    /// large ruleset never match a given piece of content, so compiling
+    /// every individual `Regex` up front (on construction *and* on every
+    /// `update_patterns`) would be wasted work.
+    regexes: Vec<OnceCell<Regex>>,
     patterns: Vec<Threat>,
+    composite_rules: Vec<CompositeRule>,
+    scoring: ScoringConfig,
+    bundle_metadata: Option<BundleMetadata>,
+    ruleset_version: u32,
 }
 
 #[wasm_bindgen]
 impl Sanitizer {
     #[wasm_bindgen(constructor)]
     pub fn new(patterns_json: &str) -> Result<Sanitizer, JsValue> {
-        let patterns: Vec<Threat> = serde_json::from_str(patterns_json)
+        Sanitizer::build(patterns_json, ScoringConfig::default()).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Like `new`, but with a data-driven scoring model instead of the
+    /// built-in severity weights and cap.
+    #[wasm_bindgen]
+    pub fn with_scoring(patterns_json: &str, scoring_json: &str) -> Result<Sanitizer, JsValue> {
+        let scoring: ScoringConfig = serde_json::from_str(scoring_json)
+            .map_err(|e| JsValue::from_str(&format!("Failed to parse scoring config: {}", e)))?;
+        Sanitizer::build(patterns_json, scoring).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Build a `Sanitizer` from a pattern bundle signed with Ed25519,
+    /// verifying the detached signature over the canonical pattern JSON
+    /// before compiling anything. `public_key` is base64-encoded.
+    #[wasm_bindgen]
+    pub fn from_signed_bundle(bundle_json: &str, public_key: &str) -> Result<Sanitizer, JsValue> {
+        Sanitizer::build_from_bundle(bundle_json, public_key).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Metadata (issuer, version, issued-at) of the signed bundle this
+    /// instance was built from, or `null` if it wasn't.
+    #[wasm_bindgen]
+    pub fn bundle_metadata(&self) -> Result<JsValue, JsValue> {
+        match &self.bundle_metadata {
+            Some(meta) => to_js_value(meta),
+            None => Ok(JsValue::NULL),
+        }
+    }
+
+    /// Load composite rules that fire on combinations, counts, or
+    /// proximity of base patterns rather than a single isolated hit.
+    #[wasm_bindgen]
+    pub fn set_composite_rules(&mut self, rules_json: &str) -> Result<(), JsValue> {
+        let rules: Vec<CompositeRule> = serde_json::from_str(rules_json)
+            .map_err(|e| JsValue::from_str(&format!("Failed to parse composite rules: {}", e)))?;
+        self.composite_rules = rules;
+        Ok(())
+    }
+
+    /// Swap in a new ruleset without dropping and reconstructing the
+    /// instance. The new `RegexSet` is compiled into a local first (which
+    /// validates every pattern; the per-pattern `Regex`es stay lazy cells);
+    /// only if it compiles do we replace the live ruleset,
+    /// so a bad push leaves the previous ruleset serving traffic and
+    /// returns the compile error instead of leaving the sanitizer broken.
+    /// Returns the names of any composite rules left referencing a
+    /// pattern the new ruleset no longer has, so a reload that silently
+    /// disables a composite detection (e.g. a base pattern got renamed)
+    /// is visible to the caller instead of failing open quietly.
+    #[wasm_bindgen]
+    pub fn update_patterns(&mut self, patterns_json: &str) -> Result<Vec<String>, JsValue> {
+        let new_patterns: Vec<Threat> = serde_json::from_str(patterns_json)
             .map_err(|e| JsValue::from_str(&format!("Failed to parse patterns: {}", e)))?;
+        let (new_regex_set, new_regexes) =
+            Sanitizer::compile_patterns(&new_patterns).map_err(|e| JsValue::from_str(&e))?;
+
+        self.regex_set = new_regex_set;
+        self.regexes = new_regexes;
+        self.patterns = new_patterns;
+        self.ruleset_version += 1;
+
+        let orphaned = self
+            .composite_rules
+            .iter()
+            .filter(|rule| {
+                rule.condition
+                    .referenced_patterns()
+                    .iter()
+                    .any(|p| self.pattern_index(p).is_none())
+            })
+            .map(|rule| rule.name.clone())
+            .collect();
+
+        Ok(orphaned)
+    }
+
+    /// Monotonically increasing version bumped on every successful
+    /// `update_patterns`, so a host can confirm which revision is live.
+    #[wasm_bindgen]
+    pub fn ruleset_version(&self) -> u32 {
+        self.ruleset_version
+    }
+
+    #[wasm_bindgen]
+    pub fn sanitize(&self, content: &str, trust_multiplier: f64) -> Result<JsValue, JsValue> {
+        let (result, _) = self.analyze(content, trust_multiplier);
+
+        to_js_value(&result)
+    }
+
+    /// Sanitize `content` and also return a copy with every matched span
+    /// replaced by a placeholder, so callers can use the cleaned text
+    /// directly instead of just getting a verdict.
+    #[wasm_bindgen]
+    pub fn sanitize_redact(
+        &self,
+        content: &str,
+        trust_multiplier: f64,
+        options_json: &str,
+    ) -> Result<JsValue, JsValue> {
+        let options: RedactOptions = if options_json.trim().is_empty() {
+            RedactOptions::default()
+        } else {
+            serde_json::from_str(options_json)
+                .map_err(|e| JsValue::from_str(&format!("Failed to parse options: {}", e)))?
+        };
+
+        let redact_result = self
+            .redact(content, trust_multiplier, &options)
+            .map_err(|e| JsValue::from_str(&e))?;
+
+        to_js_value(&redact_result)
+    }
+
+    /// Sanitize several named documents (e.g. a system prompt plus every
+    /// tool result in a turn) in one call and return a combined report, so
+    /// callers don't have to issue N calls and stitch the results together.
+    #[wasm_bindgen]
+    pub fn sanitize_batch(&self, docs_json: &str) -> Result<JsValue, JsValue> {
+        let docs: Vec<BatchDoc> = serde_json::from_str(docs_json)
+            .map_err(|e| JsValue::from_str(&format!("Failed to parse documents: {}", e)))?;
+
+        let mut documents = Vec::with_capacity(docs.len());
+        let mut threats = Vec::new();
+        let mut worst_risk_score: f64 = 0.0;
+        let mut summed_risk_score: f64 = 0.0;
+        let mut safe = true;
+
+        for doc in docs {
+            let (result, _) = self.analyze(&doc.content, doc.trust_multiplier);
+
+            safe = safe && result.safe;
+            worst_risk_score = worst_risk_score.max(result.risk_score);
+            summed_risk_score += result.risk_score;
 
-        let mut regex_strs = Vec::new();
-        for p in &patterns {
-            regex_strs.push(p.pattern.clone());
+            for threat in &result.threats {
+                threats.push(SourcedThreatMatch {
+                    pattern: threat.pattern.clone(),
+                    category: threat.category.clone(),
+                    severity: threat.severity.clone(),
+                    matched_text: threat.matched_text.clone(),
+                    byte_offset: threat.byte_offset,
+                    line: threat.line,
+                    column: threat.column,
+                    source: doc.name.clone(),
+                });
+            }
+
+            documents.push(DocumentSanitizeResult {
+                name: doc.name,
+                result,
+            });
         }
 
+        let batch_result = BatchSanitizeResult {
+            safe,
+            documents,
+            threats,
+            worst_risk_score,
+            summed_risk_score,
+        };
+
+        to_js_value(&batch_result)
+    }
+}
+
+impl Sanitizer {
+    /// Shared construction path for `new`, `with_scoring`, and
+    /// `build_from_bundle`: compile the `RegexSet` and set up lazy
+    /// per-pattern regex cells, used identically either way. Kept free of
+    /// `JsValue` so it's usable from tests without a JS host.
+    fn build(patterns_json: &str, scoring: ScoringConfig) -> Result<Sanitizer, String> {
+        let patterns: Vec<Threat> = serde_json::from_str(patterns_json)
+            .map_err(|e| format!("Failed to parse patterns: {}", e))?;
+        let (regex_set, regexes) = Sanitizer::compile_patterns(&patterns)?;
+
+        Ok(Sanitizer {
+            regex_set,
+            regexes,
+            patterns,
+            composite_rules: Vec::new(),
+            scoring,
+            bundle_metadata: None,
+            ruleset_version: 0,
+        })
+    }
+
+    /// Core logic behind `from_signed_bundle`, kept free of `JsValue` so
+    /// the signature-verification path can be exercised directly from
+    /// tests instead of only through the wasm boundary.
+    fn build_from_bundle(bundle_json: &str, public_key: &str) -> Result<Sanitizer, String> {
+        let bundle: SignedPatternBundle = serde_json::from_str(bundle_json)
+            .map_err(|e| format!("Failed to parse bundle: {}", e))?;
+
+        let canonical = serde_json::to_vec(&bundle.patterns)
+            .map_err(|e| format!("Failed to canonicalize patterns: {}", e))?;
+
+        let key_bytes = BASE64
+            .decode(public_key)
+            .map_err(|e| format!("Invalid public key encoding: {}", e))?;
+        let key_array: [u8; 32] = key_bytes
+            .try_into()
+            .map_err(|_| "Public key must be 32 bytes".to_string())?;
+        let verifying_key = VerifyingKey::from_bytes(&key_array)
+            .map_err(|e| format!("Invalid public key: {}", e))?;
+
+        let sig_bytes = BASE64
+            .decode(&bundle.signature)
+            .map_err(|e| format!("Invalid signature encoding: {}", e))?;
+        let sig_array: [u8; 64] = sig_bytes
+            .try_into()
+            .map_err(|_| "Signature must be 64 bytes".to_string())?;
+        let signature = Signature::from_bytes(&sig_array);
+
+        verifying_key
+            .verify(&canonical, &signature)
+            .map_err(|_| "Bundle signature verification failed".to_string())?;
+
+        let patterns_json = serde_json::to_string(&bundle.patterns)
+            .map_err(|e| format!("Failed to serialize patterns: {}", e))?;
+
+        let mut sanitizer = Sanitizer::build(&patterns_json, ScoringConfig::default())?;
+        sanitizer.bundle_metadata = Some(bundle.metadata);
+        Ok(sanitizer)
+    }
+
+    /// Compile the `RegexSet` (cheap "does anything match" pass) eagerly,
+    /// since every pattern needs to participate in it on every scan. The
+    /// per-pattern `Regex`es are only needed for the handful of patterns
+    /// that actually hit, so they're left as empty cells here and compiled
+    /// lazily via `regex_at`. Shared by construction and `update_patterns`
+    /// so a reload always validates the exact same way a fresh instance
+    /// would.
+    fn compile_patterns(patterns: &[Threat]) -> Result<(RegexSet, Vec<OnceCell<Regex>>), String> {
+        let regex_strs: Vec<String> = patterns.iter().map(|p| p.pattern.clone()).collect();
+
         let regex_set = regex::RegexSetBuilder::new(&regex_strs)
             .case_insensitive(true)
             .size_limit(10 * (1 << 20))
             .build()
-            .map_err(|e| JsValue::from_str(&format!("Failed to compile regex set: {}", e)))?;
+            .map_err(|e| format!("Failed to compile regex set: {}", e))?;
+
+        let regexes = regex_strs.iter().map(|_| OnceCell::new()).collect();
 
-        Ok(Sanitizer { regex_set, patterns })
+        Ok((regex_set, regexes))
     }
 
-    #[wasm_bindgen]
-    pub fn sanitize(&self, content: &str, trust_multiplier: f64) -> Result<JsValue, JsValue> {
+    /// The compiled `Regex` for pattern `i`, compiling it on first use.
+    /// `RegexSet` already validated that every pattern (including this
+    /// one) compiles as a regex, so a failure here would mean the two
+    /// builders disagree — surfaced as an error rather than panicking.
+    fn regex_at(&self, i: usize) -> Result<&Regex, String> {
+        self.regexes[i].get_or_try_init(|| {
+            RegexBuilder::new(&self.patterns[i].pattern)
+                .case_insensitive(true)
+                .build()
+                .map_err(|e| format!("Failed to compile regex: {}", e))
+        })
+    }
+
+    /// Weight for one matched severity/category, after category
+    /// multiplier and diminishing returns. `severity_counts` tracks how
+    /// many hits of each severity have already been scored this pass.
+    fn scored_weight(
+        &self,
+        severity: &str,
+        category: &str,
+        severity_counts: &mut HashMap<String, usize>,
+    ) -> f64 {
+        let base = *self.scoring.severity_weights.get(severity).unwrap_or(&0.0);
+        let multiplier = *self
+            .scoring
+            .category_multipliers
+            .get(category)
+            .unwrap_or(&1.0);
+        let mut weight = base * multiplier;
+
+        let count = severity_counts.entry(severity.to_string()).or_insert(0);
+        if let Some(dr) = &self.scoring.diminishing_returns {
+            if *count >= dr.threshold {
+                weight *= dr.factor;
+            }
+        }
+        *count += 1;
+
+        weight
+    }
+
+    /// Shared scoring pass used by both `sanitize` and `sanitize_redact`.
+    /// Returns the result alongside the indices into `self.patterns` that
+    /// matched, so callers needing match spans don't have to re-scan.
+    fn analyze(&self, content: &str, trust_multiplier: f64) -> (SanitizeResult, Vec<usize>) {
         let mut threats = Vec::new();
         let mut risk_score = 0.0;
+        let mut severity_counts: HashMap<String, usize> = HashMap::new();
+        let matched_indices: Vec<usize> = self.regex_set.matches(content).into_iter().collect();
 
-        let matches = self.regex_set.matches(content);
-        for i in matches.into_iter() {
+        for &i in &matched_indices {
             let matched_pattern = &self.patterns[i];
-            threats.push(matched_pattern.clone());
-
-            let weight = match matched_pattern.severity.as_str() {
-                "critical" => 10.0,
-                "high" => 5.0,
-                "medium" => 3.0,
-                "low" => 1.0,
-                _ => 0.0,
-            };
-            risk_score += weight;
+
+            // The RegexSet only tells us *that* pattern `i` matched; fall
+            // back to its individual Regex (compiled lazily here, on
+            // first hit) to find *where*, for reporting. If that somehow
+            // comes back empty, or the lazy compile itself fails, skip
+            // scoring it too, so risk_score never counts a threat we
+            // don't also report.
+            if let Some(m) = self.regex_at(i).ok().and_then(|re| re.find(content)) {
+                let (line, column) = line_col(content, m.start());
+                threats.push(ThreatMatch {
+                    pattern: matched_pattern.pattern.clone(),
+                    category: matched_pattern.category.clone(),
+                    severity: matched_pattern.severity.clone(),
+                    matched_text: m.as_str().to_string(),
+                    byte_offset: m.start(),
+                    line,
+                    column,
+                });
+
+                risk_score += self.scored_weight(
+                    &matched_pattern.severity,
+                    &matched_pattern.category,
+                    &mut severity_counts,
+                );
+            }
+        }
+
+        for rule in &self.composite_rules {
+            if let Some(m) = self.evaluate_composite(rule, content, &matched_indices) {
+                risk_score += self.scored_weight(&rule.severity, &rule.category, &mut severity_counts);
+                threats.push(m);
+            }
         }
 
         risk_score *= trust_multiplier;
-        if risk_score > 100.0 {
-            risk_score = 100.0;
+        if risk_score > self.scoring.cap {
+            risk_score = self.scoring.cap;
         }
 
         let result = SanitizeResult {
             safe: threats.is_empty(),
             threats,
             risk_score,
+            effective_weights: self.scoring.severity_weights.clone(),
+            category_multipliers: self.scoring.category_multipliers.clone(),
+            diminishing_returns: self.scoring.diminishing_returns.clone(),
+            cap: self.scoring.cap,
         };
 
-        serde_wasm_bindgen::to_value(&result)
-            .map_err(|e| JsValue::from_str(&format!("Failed to serialize result: {}", e)))
+        (result, matched_indices)
+    }
+
+    /// Core logic behind `sanitize_redact`, split out so it can be driven
+    /// directly from tests without going through JSON/`JsValue` at the
+    /// boundary. Merges overlapping and adjacent matched spans (a span
+    /// starting at or before the previous span's end) into one redaction
+    /// so e.g. two patterns matching the same substring don't produce two
+    /// back-to-back placeholders.
+    fn redact(
+        &self,
+        content: &str,
+        trust_multiplier: f64,
+        options: &RedactOptions,
+    ) -> Result<SanitizeRedactResult, String> {
+        let (result, matched_indices) = self.analyze(content, trust_multiplier);
+
+        let mut raw_spans: Vec<(usize, usize, String)> = Vec::new();
+        for &i in &matched_indices {
+            let category = self.patterns[i].category.clone();
+            for m in self.regex_at(i)?.find_iter(content) {
+                raw_spans.push((m.start(), m.end(), category.clone()));
+            }
+        }
+        raw_spans.sort_by_key(|s| s.0);
+
+        let mut redactions: Vec<RedactedSpan> = Vec::new();
+        for (start, end, category) in raw_spans {
+            match redactions.last_mut() {
+                Some(last) if start <= last.end => {
+                    last.end = last.end.max(end);
+                    if !last.categories.contains(&category) {
+                        last.categories.push(category);
+                    }
+                }
+                _ => redactions.push(RedactedSpan {
+                    start,
+                    end,
+                    categories: vec![category],
+                }),
+            }
+        }
+
+        let mut redacted = String::with_capacity(content.len());
+        let mut cursor = 0;
+        for span in &redactions {
+            // `start`/`end` come from regex match byte offsets, which are
+            // always aligned to UTF-8 char boundaries, so these slices
+            // never split a multi-byte character.
+            redacted.push_str(&content[cursor..span.start]);
+            let placeholder = options
+                .placeholder_template
+                .replace("{category}", &span.categories.join("+"));
+            redacted.push_str(&placeholder);
+            cursor = span.end;
+        }
+        redacted.push_str(&content[cursor..]);
+
+        Ok(SanitizeRedactResult {
+            result,
+            redacted,
+            redactions,
+        })
+    }
+
+    fn pattern_index(&self, pattern: &str) -> Option<usize> {
+        self.patterns.iter().position(|p| p.pattern == pattern)
+    }
+
+    /// Check whether a composite rule's condition holds and, if so, build
+    /// the `ThreatMatch` it fires. `matched_indices` is the cheap
+    /// `RegexSet` pass already computed for this content.
+    fn evaluate_composite(
+        &self,
+        rule: &CompositeRule,
+        content: &str,
+        matched_indices: &[usize],
+    ) -> Option<ThreatMatch> {
+        let fires_at = |pattern: &str| -> Option<regex::Match> {
+            let i = self.pattern_index(pattern)?;
+            if !matched_indices.contains(&i) {
+                return None;
+            }
+            self.regex_at(i).ok()?.find(content)
+        };
+
+        // Each arm decides whether the condition holds and, if so, the
+        // anchor span to report the composite hit at (None means "holds,
+        // but there's no single matched span to point at").
+        let anchor: Option<Option<regex::Match>> = match &rule.condition {
+            CompositeCondition::AllOf { patterns } => {
+                let mut first = None;
+                for p in patterns {
+                    let m = fires_at(p)?;
+                    first.get_or_insert(m);
+                }
+                Some(first)
+            }
+            CompositeCondition::AnyOf { patterns } => {
+                patterns.iter().find_map(|p| fires_at(p)).map(Some)
+            }
+            CompositeCondition::NoneOf { patterns } => {
+                if patterns.iter().any(|p| fires_at(p).is_some()) {
+                    None
+                } else {
+                    Some(None)
+                }
+            }
+            CompositeCondition::MinCount { pattern, count } => {
+                let i = self.pattern_index(pattern)?;
+                let occurrences: Vec<_> = self.regex_at(i).ok()?.find_iter(content).collect();
+                if occurrences.len() < *count {
+                    None
+                } else {
+                    Some(occurrences.into_iter().next())
+                }
+            }
+            CompositeCondition::Proximity {
+                pattern_a,
+                pattern_b,
+                within_bytes,
+            } => {
+                let i = self.pattern_index(pattern_a)?;
+                let j = self.pattern_index(pattern_b)?;
+                if !matched_indices.contains(&i) || !matched_indices.contains(&j) {
+                    return None;
+                }
+                let spans_a: Vec<_> = self.regex_at(i).ok()?.find_iter(content).collect();
+                let spans_b: Vec<_> = self.regex_at(j).ok()?.find_iter(content).collect();
+                let close_pair = spans_a.iter().find_map(|a| {
+                    spans_b.iter().find_map(|b| {
+                        let gap = if a.end() <= b.start() {
+                            b.start() - a.end()
+                        } else if b.end() <= a.start() {
+                            a.start() - b.end()
+                        } else {
+                            0
+                        };
+                        (gap <= *within_bytes).then_some(*a)
+                    })
+                });
+                close_pair.map(Some)
+            }
+        };
+
+        let anchor = anchor?;
+        let (matched_text, byte_offset, line, column) = match anchor {
+            Some(m) => {
+                let (line, column) = line_col(content, m.start());
+                (m.as_str().to_string(), m.start(), line, column)
+            }
+            None => (String::new(), 0, 1, 1),
+        };
+
+        Some(ThreatMatch {
+            pattern: rule.name.clone(),
+            category: rule.category.clone(),
+            severity: rule.severity.clone(),
+            matched_text,
+            byte_offset,
+            line,
+            column,
+        })
+    }
+}
+
+/// 1-based line and column of the byte offset `pos` within `content`,
+/// counting newlines up to `pos` the same way an editor would.
+fn line_col(content: &str, pos: usize) -> (usize, usize) {
+    let prefix = &content[..pos];
+    let line = prefix.matches('\n').count() + 1;
+    let column = match prefix.rfind('\n') {
+        Some(last_newline) => prefix[last_newline + 1..].chars().count() + 1,
+        None => prefix.chars().count() + 1,
+    };
+    (line, column)
+}
+
+#[cfg(test)]
+mod signed_bundle_tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+    use rand::rngs::OsRng;
+
+    fn signed_bundle_json(patterns: &[Threat], signing_key: &SigningKey) -> String {
+        let canonical = serde_json::to_vec(patterns).unwrap();
+        let signature = signing_key.sign(&canonical);
+        let bundle = serde_json::json!({
+            "patterns": patterns,
+            "metadata": {
+                "issuer": "test-issuer",
+                "version": "1",
+                "issued_at": "2026-01-01T00:00:00Z",
+            },
+            "signature": BASE64.encode(signature.to_bytes()),
+        });
+        serde_json::to_string(&bundle).unwrap()
+    }
+
+    #[test]
+    fn valid_signature_is_accepted() {
+        let signing_key = SigningKey::generate(&mut OsRng);
+        let patterns = vec![Threat {
+            pattern: "foo".to_string(),
+            category: "test".to_string(),
+            severity: "low".to_string(),
+        }];
+        let bundle_json = signed_bundle_json(&patterns, &signing_key);
+        let pk_b64 = BASE64.encode(signing_key.verifying_key().to_bytes());
+
+        assert!(Sanitizer::build_from_bundle(&bundle_json, &pk_b64).is_ok());
+    }
+
+    #[test]
+    fn tampered_patterns_are_rejected() {
+        let signing_key = SigningKey::generate(&mut OsRng);
+        let patterns = vec![Threat {
+            pattern: "foo".to_string(),
+            category: "test".to_string(),
+            severity: "low".to_string(),
+        }];
+        let bundle_json = signed_bundle_json(&patterns, &signing_key);
+        let pk_b64 = BASE64.encode(signing_key.verifying_key().to_bytes());
+
+        // Swap the signed pattern for a different one after signing, so
+        // the signature no longer matches the canonical bytes it covers.
+        let tampered = bundle_json.replace("\"foo\"", "\"evil\"");
+
+        assert!(Sanitizer::build_from_bundle(&tampered, &pk_b64).is_err());
+    }
+
+    #[test]
+    fn wrong_public_key_is_rejected() {
+        let signing_key = SigningKey::generate(&mut OsRng);
+        let other_key = SigningKey::generate(&mut OsRng);
+        let patterns = vec![Threat {
+            pattern: "foo".to_string(),
+            category: "test".to_string(),
+            severity: "low".to_string(),
+        }];
+        let bundle_json = signed_bundle_json(&patterns, &signing_key);
+        let wrong_pk_b64 = BASE64.encode(other_key.verifying_key().to_bytes());
+
+        assert!(Sanitizer::build_from_bundle(&bundle_json, &wrong_pk_b64).is_err());
+    }
+}
+
+#[cfg(test)]
+mod redact_tests {
+    use super::*;
+
+    fn sanitizer(patterns: &str) -> Sanitizer {
+        Sanitizer::build(patterns, ScoringConfig::default()).unwrap()
+    }
+
+    #[test]
+    fn multi_byte_utf8_span_is_redacted_without_splitting_chars() {
+        let s = sanitizer(
+            r#"[{"pattern":"secret","category":"test","severity":"low"}]"#,
+        );
+        let content = "日本語 secret データ";
+
+        let result = s.redact(content, 1.0, &RedactOptions::default()).unwrap();
+
+        assert_eq!(result.redactions.len(), 1);
+        assert_eq!(result.redacted, "日本語 [REDACTED:test] データ");
+    }
+
+    #[test]
+    fn overlapping_spans_are_merged_into_one_redaction() {
+        let s = sanitizer(
+            r#"[{"pattern":"foobar","category":"a","severity":"low"},{"pattern":"barbaz","category":"b","severity":"low"}]"#,
+        );
+        let content = "foobarbaz";
+
+        let result = s.redact(content, 1.0, &RedactOptions::default()).unwrap();
+
+        assert_eq!(result.redactions.len(), 1);
+        assert_eq!(result.redactions[0].start, 0);
+        assert_eq!(result.redactions[0].end, 9);
+        assert_eq!(result.redactions[0].categories, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn adjacent_spans_are_merged_into_one_redaction() {
+        let s = sanitizer(
+            r#"[{"pattern":"foo","category":"a","severity":"low"},{"pattern":"bar","category":"b","severity":"low"}]"#,
+        );
+        let content = "foobar";
+
+        let result = s.redact(content, 1.0, &RedactOptions::default()).unwrap();
+
+        assert_eq!(result.redactions.len(), 1);
+        assert_eq!(result.redactions[0].start, 0);
+        assert_eq!(result.redactions[0].end, 6);
     }
 }